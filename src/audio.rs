@@ -1,17 +1,125 @@
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, Sample, Stream, StreamConfig};
+use realfft::RealFftPlanner;
+use rustfft::num_complex::Complex32;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Frame size used by the voice-activity detector, in milliseconds.
+const VAD_FRAME_MS: u32 = 30;
+/// How much leading audio to average to estimate the ambient noise floor.
+const VAD_NOISE_FLOOR_MS: u32 = 300;
+/// Speech is declared once frame energy exceeds this multiple of the noise floor.
+const VAD_THRESHOLD_MULTIPLIER: f32 = 3.5;
+/// Consecutive above-threshold frames required to confirm speech has started.
+const VAD_ONSET_FRAMES: usize = 3;
+/// How long (in ms) frame energy must stay below threshold to confirm the utterance has ended.
+const VAD_HANGOVER_MS: u32 = 700;
+/// Speech band used when scoring frame energy, to reject low-frequency rumble.
+const VAD_SPEECH_BAND_HZ: (f32, f32) = (300.0, 3400.0);
+/// Upper bound on how long to wait for speech onset before giving up, so a
+/// quiet room or a muted/bad mic doesn't block forever with no feedback.
+const VAD_MAX_ONSET_WAIT_MS: u32 = 10_000;
+
+enum VadState {
+    WaitingForOnset { consecutive_above: usize },
+    InSpeech { silence_frames: usize },
+}
+
+/// Scores frame energy using the power spectrum summed over the speech band,
+/// reusing one FFT plan across every frame of a recording.
+struct SpeechBandEnergy {
+    fft: Arc<dyn realfft::RealToComplex<f32>>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    low_bin: usize,
+    high_bin: usize,
+}
+
+impl SpeechBandEnergy {
+    fn new(frame_len: usize, sample_rate: u32) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(frame_len);
+        let spectrum = fft.make_output_vec();
+
+        let bin_hz = sample_rate as f32 / frame_len as f32;
+        let (low_hz, high_hz) = VAD_SPEECH_BAND_HZ;
+        let low_bin = (low_hz / bin_hz).floor() as usize;
+        let high_bin = ((high_hz / bin_hz).ceil() as usize).min(spectrum.len() - 1);
+
+        Self {
+            fft,
+            input: vec![0.0; frame_len],
+            spectrum,
+            low_bin,
+            high_bin,
+        }
+    }
+
+    fn energy(&mut self, frame: &[f32]) -> f32 {
+        self.input.copy_from_slice(frame);
+        self.fft
+            .process(&mut self.input, &mut self.spectrum)
+            .expect("realfft: frame length mismatch");
+
+        self.spectrum[self.low_bin..=self.high_bin]
+            .iter()
+            .map(|c| c.norm_sqr())
+            .sum()
+    }
+}
+
+/// Producer/consumer ring buffer shared between whatever is generating
+/// samples (e.g. a TTS codec) and the cpal output callback that drains them.
+/// `produce` can keep appending while playback has already started, so audio
+/// can start before the whole response is ready.
+struct RingBuffer {
+    samples: Mutex<VecDeque<f32>>,
+    end_of_stream: AtomicBool,
+}
+
+impl RingBuffer {
+    fn new() -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::new()),
+            end_of_stream: AtomicBool::new(false),
+        }
+    }
+
+    fn produce(&self, chunk: &[f32]) {
+        self.samples.lock().unwrap().extend(chunk.iter().copied());
+    }
+
+    /// Marks that no more samples are coming, so a drained buffer means
+    /// playback is actually done rather than just temporarily starved.
+    fn mark_end_of_stream(&self) {
+        self.end_of_stream.store(true, Ordering::Relaxed);
+    }
+
+    /// Fill `out` from the buffer, writing silence for any samples not yet
+    /// produced (an underrun) instead of blocking the audio callback.
+    fn consume_exact(&self, out: &mut [f32]) {
+        let mut samples = self.samples.lock().unwrap();
+        for sample in out.iter_mut() {
+            *sample = samples.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    fn is_drained(&self) -> bool {
+        self.end_of_stream.load(Ordering::Relaxed) && self.samples.lock().unwrap().is_empty()
+    }
+}
+
 pub struct OutputStream {
     stream: Stream,
-    finished: Arc<AtomicBool>,
+    ring: Arc<RingBuffer>,
 }
 
 impl OutputStream {
-    pub fn new(stream: Stream, finished: Arc<AtomicBool>) -> Self {
-        Self { stream, finished }
+    fn new(stream: Stream, ring: Arc<RingBuffer>) -> Self {
+        Self { stream, ring }
     }
 
     pub fn play(&self) -> Result<()> {
@@ -19,14 +127,26 @@ impl OutputStream {
         Ok(())
     }
 
+    /// Append more samples to play. Can be called repeatedly while the
+    /// stream is already playing, so output starts before all of it exists.
+    pub fn produce(&self, chunk: &[f32]) {
+        self.ring.produce(chunk);
+    }
+
+    /// Signal that no more samples are coming, so `wait` returns once the
+    /// buffer finishes draining instead of waiting forever.
+    pub fn end_of_stream(&self) {
+        self.ring.mark_end_of_stream();
+    }
+
     pub fn stop(self) -> Result<()> {
         self.stream.pause()?;
-        self.finished.store(true, Ordering::Relaxed);
+        self.ring.mark_end_of_stream();
         Ok(())
     }
 
     pub fn wait(&self) {
-        while !self.finished.load(Ordering::Relaxed) {
+        while !self.ring.is_drained() {
             std::thread::sleep(std::time::Duration::from_millis(10));
         }
 
@@ -35,6 +155,95 @@ impl OutputStream {
     }
 }
 
+/// Number of input taps on either side of the sinc kernel's center tap. This
+/// equals the kernel's zero crossings per side only when `cutoff` is 1.0.
+const SINC_HALF_WIDTH: isize = 16;
+
+/// Blackman window over `[-half_width, half_width]`, evaluated at integer `k`.
+fn blackman_window(k: isize, half_width: isize) -> f64 {
+    let t = (k + half_width) as f64 / (2.0 * half_width as f64);
+    0.42 - 0.5 * (2.0 * std::f64::consts::PI * t).cos() + 0.08 * (4.0 * std::f64::consts::PI * t).cos()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-8 {
+        1.0
+    } else {
+        let px = std::f64::consts::PI * x;
+        px.sin() / px
+    }
+}
+
+/// Resample `input` from `src_rate` to `target_rate` with a windowed-sinc
+/// polyphase filter, as used by e.g. `rubato`. The window is precomputed once
+/// since it only depends on tap offset `k`, not on the fractional source
+/// position; the sinc term still varies per output sample.
+fn sinc_resample(input: &[f32], src_rate: f64, target_rate: f64) -> Vec<f32> {
+    if input.is_empty() {
+        return Vec::new();
+    }
+
+    let ratio = src_rate / target_rate;
+    // Below the new Nyquist when downsampling, so the kernel's passband never
+    // aliases; left at 1.0 (no extra attenuation) when upsampling.
+    let cutoff = (target_rate / src_rate).min(1.0);
+    let output_len = (input.len() as f64 / ratio) as usize;
+
+    let window: Vec<f64> = (-SINC_HALF_WIDTH..=SINC_HALF_WIDTH)
+        .map(|k| blackman_window(k, SINC_HALF_WIDTH))
+        .collect();
+
+    let last_index = input.len() as isize - 1;
+    let mut output = Vec::with_capacity(output_len);
+
+    for i in 0..output_len {
+        let t = i as f64 * ratio;
+        let center = t.floor() as isize;
+
+        let mut acc = 0.0f64;
+        for (w_idx, k) in (-SINC_HALF_WIDTH..=SINC_HALF_WIDTH).enumerate() {
+            let tap = (center + k).clamp(0, last_index) as usize;
+            let offset = t - (center + k) as f64;
+            acc += input[tap] as f64 * cutoff * sinc(cutoff * offset) * window[w_idx];
+        }
+        output.push(acc as f32);
+    }
+
+    output
+}
+
+/// A live microphone capture in progress. Exposes the buffer cpal is filling
+/// and a "finished" flag that flips once capture stops, so callers can drive
+/// something like `Stt::transcribe_stream` off the same data while it's
+/// still being recorded, rather than waiting for the final `Vec<f32>`.
+pub struct Capture {
+    // Never read again after construction; kept only so the stream (and its
+    // callback) stays alive for as long as the `Capture` does.
+    _stream: Stream,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    recording: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Capture {
+    pub fn buffer(&self) -> &Arc<Mutex<Vec<f32>>> {
+        &self.buffer
+    }
+
+    /// Set once `stop` has been called; doubles as the "finished" signal
+    /// `Stt::transcribe_stream` expects.
+    pub fn finished(&self) -> &Arc<AtomicBool> {
+        &self.finished
+    }
+
+    /// Stop feeding new samples into the buffer and mark the capture
+    /// finished, e.g. once VAD decides the user is done speaking.
+    pub fn stop(&self) {
+        self.recording.store(false, Ordering::Relaxed);
+        self.finished.store(true, Ordering::Relaxed);
+    }
+}
+
 pub struct Audio {
     input_device: Device,
     output_device: Device,
@@ -67,6 +276,11 @@ impl Audio {
             "Using input config: {} channels, {} Hz (will resample to 16kHz)",
             input_config.channels, input_sample_rate
         );
+        anyhow::ensure!(
+            matches!(input_config.channels, 1 | 2),
+            "unsupported input device: {} channels (only mono or stereo are supported)",
+            input_config.channels
+        );
 
         let output_config = output_device.default_output_config()?.into();
 
@@ -79,62 +293,99 @@ impl Audio {
         })
     }
 
-    /// Resample audio from source sample rate to target sample rate using linear interpolation
+    /// Resample audio from source sample rate to target sample rate using a
+    /// windowed-sinc (Blackman) polyphase filter. Downsampling lowers the
+    /// kernel's cutoff below the target Nyquist frequency, which keeps linear
+    /// interpolation's aliasing out of the resampled signal.
     fn resample_audio(&self, audio_data: &[f32], target_sample_rate: u32) -> Vec<f32> {
         if self.input_sample_rate == target_sample_rate {
             return audio_data.to_vec();
         }
 
-        let ratio = self.input_sample_rate as f64 / target_sample_rate as f64;
-        let output_length = (audio_data.len() as f64 / ratio) as usize;
-        let mut resampled = Vec::with_capacity(output_length);
-
-        for i in 0..output_length {
-            let src_index = i as f64 * ratio;
-            let src_index_floor = src_index.floor() as usize;
-            let src_index_ceil = (src_index_floor + 1).min(audio_data.len() - 1);
-            let fraction = src_index - src_index_floor as f64;
-
-            if src_index_floor < audio_data.len() {
-                let sample1 = audio_data[src_index_floor];
-                let sample2 = audio_data[src_index_ceil];
-                let interpolated = sample1 + (sample2 - sample1) * fraction as f32;
-                resampled.push(interpolated);
-            }
-        }
+        sinc_resample(
+            audio_data,
+            self.input_sample_rate as f64,
+            target_sample_rate as f64,
+        )
+    }
+
+    /// Start capturing microphone input into a shared buffer without
+    /// blocking. The caller decides when to stop (see `Capture::stop`) and
+    /// can read the buffer, or stream it elsewhere (e.g.
+    /// `Stt::transcribe_stream`), while capture is still in progress.
+    pub fn begin_capture(&self) -> Result<Capture> {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let recording = Arc::new(AtomicBool::new(true));
+        let finished = Arc::new(AtomicBool::new(false));
 
-        resampled
+        let stream =
+            self.build_input_stream::<f32>(Arc::clone(&buffer), Arc::clone(&recording))?;
+        stream.play()?;
+
+        Ok(Capture {
+            _stream: stream,
+            buffer,
+            recording,
+            finished,
+        })
+    }
+
+    /// Blocks until the voice-activity detector decides the speaker has
+    /// stopped (or gives up after `VAD_MAX_ONSET_WAIT_MS` of silence),
+    /// without marking `capture` finished. Exposed on top of
+    /// `record_until_silence` so callers can run something like
+    /// `Stt::transcribe_stream` against `capture` concurrently.
+    pub fn wait_for_silence(&self, capture: &Capture) {
+        let channels = self.input_config.channels as usize;
+        self.wait_for_silence_impl(&capture.buffer, channels);
     }
 
     pub fn record_until_enter(&self) -> Result<Vec<f32>> {
         let channels = self.input_config.channels as usize;
-        let audio_data = Arc::new(Mutex::new(Vec::new()));
-        let recording = Arc::new(AtomicBool::new(true));
+        let capture = self.begin_capture()?;
 
-        {
-            let stream =
-                self.build_input_stream::<f32>(Arc::clone(&audio_data), Arc::clone(&recording))?;
-            stream.play()?;
+        println!("Recording... Press Enter to stop.");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).unwrap();
+        capture.stop();
 
-            println!("Recording... Press Enter to stop.");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
+        let recorded_data = capture.buffer().lock().unwrap().clone();
 
-            // Stop recording
-            recording.store(false, Ordering::Relaxed);
-        }
+        Ok(self.finish_recording(recorded_data, channels))
+    }
 
-        let recorded_data = audio_data.lock().unwrap().clone();
+    /// Like `record_until_enter`, but stops automatically once a voice-activity
+    /// detector decides the user has finished speaking, rather than waiting for Enter.
+    pub fn record_until_silence(&self) -> Result<Vec<f32>> {
+        let channels = self.input_config.channels as usize;
+        let capture = self.begin_capture()?;
 
-        // Convert to mono if stereo
-        let mono_data = if channels == 2 {
-            recorded_data
-                .chunks(2)
+        println!("Recording... (stops automatically when you finish speaking)");
+        self.wait_for_silence(&capture);
+        capture.stop();
+
+        let recorded_data = capture.buffer().lock().unwrap().clone();
+
+        Ok(self.finish_recording(recorded_data, channels))
+    }
+
+    /// Downmix interleaved multi-channel audio to mono by averaging channels.
+    /// Channel counts other than 1 or 2 are left interleaved, matching the
+    /// rest of this module's mono/stereo-only support.
+    fn downmix_to_mono(data: &[f32], channels: usize) -> Vec<f32> {
+        if channels == 2 {
+            data.chunks(2)
                 .map(|chunk| (chunk[0] + chunk[1]) / 2.0)
                 .collect()
         } else {
-            recorded_data
-        };
+            data.to_vec()
+        }
+    }
+
+    /// Convert raw captured samples to mono and resample them to 16kHz, the
+    /// format expected by the rest of the pipeline.
+    fn finish_recording(&self, recorded_data: Vec<f32>, channels: usize) -> Vec<f32> {
+        let mono_data = Self::downmix_to_mono(&recorded_data, channels);
 
         // Resample to 16kHz
         let resampled_data = self.resample_audio(&mono_data, 16000);
@@ -144,9 +395,112 @@ impl Audio {
             self.input_sample_rate
         );
 
-        Ok(resampled_data)
+        resampled_data
+    }
+
+    /// Blocks until the voice-activity detector observes onset followed by a
+    /// trailing silence hangover, polling the shared buffer as the input
+    /// stream's callback fills it. Gives up and returns if speech never
+    /// starts within `VAD_MAX_ONSET_WAIT_MS`, so a quiet room or a bad mic
+    /// doesn't hang forever.
+    fn wait_for_silence_impl(&self, audio_data: &Arc<Mutex<Vec<f32>>>, channels: usize) {
+        let raw_frame_len =
+            (self.input_sample_rate as usize * VAD_FRAME_MS as usize / 1000) * channels;
+        let mono_frame_len = raw_frame_len / channels;
+        let noise_floor_frames = (VAD_NOISE_FLOOR_MS / VAD_FRAME_MS).max(1) as usize;
+        let hangover_frames = (VAD_HANGOVER_MS / VAD_FRAME_MS).max(1) as usize;
+        let max_onset_frames = (VAD_MAX_ONSET_WAIT_MS / VAD_FRAME_MS).max(1) as usize;
+
+        let mut band_energy = SpeechBandEnergy::new(mono_frame_len, self.input_sample_rate);
+        let mut noise_floor_samples = Vec::with_capacity(noise_floor_frames);
+        let mut noise_floor = 0.0f32;
+        let mut state = VadState::WaitingForOnset {
+            consecutive_above: 0,
+        };
+        let mut processed = 0usize;
+        let mut onset_wait_frames = 0usize;
+
+        loop {
+            let raw_frame = {
+                let data = audio_data.lock().unwrap();
+                if data.len() < processed + raw_frame_len {
+                    None
+                } else {
+                    Some(data[processed..processed + raw_frame_len].to_vec())
+                }
+            };
+
+            let Some(raw_frame) = raw_frame else {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                continue;
+            };
+            processed += raw_frame_len;
+
+            let frame = Self::downmix_to_mono(&raw_frame, channels);
+            let energy = band_energy.energy(&frame);
+
+            if noise_floor_samples.len() < noise_floor_frames {
+                noise_floor_samples.push(energy);
+                noise_floor =
+                    noise_floor_samples.iter().sum::<f32>() / noise_floor_samples.len() as f32;
+                continue;
+            }
+
+            let threshold = noise_floor * VAD_THRESHOLD_MULTIPLIER;
+
+            match &mut state {
+                VadState::WaitingForOnset { consecutive_above } => {
+                    onset_wait_frames += 1;
+                    if onset_wait_frames >= max_onset_frames {
+                        println!(
+                            "No speech detected after {}ms, stopping.",
+                            VAD_MAX_ONSET_WAIT_MS
+                        );
+                        return;
+                    }
+
+                    if energy > threshold {
+                        *consecutive_above += 1;
+                        if *consecutive_above >= VAD_ONSET_FRAMES {
+                            state = VadState::InSpeech { silence_frames: 0 };
+                        }
+                    } else {
+                        *consecutive_above = 0;
+                    }
+                }
+                VadState::InSpeech { silence_frames } => {
+                    if energy > threshold {
+                        *silence_frames = 0;
+                    } else {
+                        *silence_frames += 1;
+                        if *silence_frames >= hangover_frames {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resample arbitrary audio (e.g. a TTS codec's waveform) to the output
+    /// device's sample rate so it can be handed to `playback` directly.
+    pub fn resample_to_output_rate(&self, audio_data: &[f32], source_sample_rate: u32) -> Vec<f32> {
+        let target_sample_rate = self.output_config.sample_rate.0;
+        if source_sample_rate == target_sample_rate {
+            return audio_data.to_vec();
+        }
+
+        sinc_resample(
+            audio_data,
+            source_sample_rate as f64,
+            target_sample_rate as f64,
+        )
     }
 
+    /// Play a complete, already-known buffer. Internally just produces the
+    /// whole thing into a streaming output and immediately marks it done;
+    /// use `playback_stream` directly to start playing before all the audio
+    /// you want to play exists.
     pub fn playback(&self, audio_data: &[f32]) -> Result<OutputStream> {
         let channels = self.output_config.channels as usize;
 
@@ -160,35 +514,30 @@ impl Audio {
             audio_data.to_vec()
         };
 
-        let playback_data = Arc::new(Mutex::new(playback_data));
-        let playback_index = Arc::new(Mutex::new(0));
-        let finished = Arc::new(AtomicBool::new(false));
+        let stream = self.playback_stream()?;
+        stream.produce(&playback_data);
+        stream.end_of_stream();
+
+        Ok(stream)
+    }
 
-        let playback_data_clone = Arc::clone(&playback_data);
-        let playback_index_clone = Arc::clone(&playback_index);
-        let finished_clone = Arc::clone(&finished);
+    /// Start an output stream backed by a ring buffer that the caller feeds
+    /// incrementally via `OutputStream::produce`, ending it with
+    /// `OutputStream::end_of_stream` once no more samples are coming.
+    pub fn playback_stream(&self) -> Result<OutputStream> {
+        let ring = Arc::new(RingBuffer::new());
+        let ring_clone = Arc::clone(&ring);
 
         let stream = self.output_device.build_output_stream(
             &self.output_config,
             move |output: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let data = playback_data_clone.lock().unwrap();
-                let mut index = playback_index_clone.lock().unwrap();
-
-                for sample in output.iter_mut() {
-                    if *index < data.len() {
-                        *sample = data[*index];
-                        *index += 1;
-                    } else {
-                        *sample = 0.0;
-                        finished_clone.store(true, Ordering::Relaxed);
-                    }
-                }
+                ring_clone.consume_exact(output);
             },
             |err| eprintln!("An error occurred on the audio output stream: {}", err),
             None,
         )?;
 
-        Ok(OutputStream::new(stream, finished))
+        Ok(OutputStream::new(stream, ring))
     }
 
     fn build_input_stream<T>(
@@ -218,3 +567,91 @@ impl Audio {
         Ok(stream)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sinc_resample_identity_when_rates_match() {
+        let input: Vec<f32> = (0..100).map(|i| (i as f32 * 0.1).sin()).collect();
+        let output = sinc_resample(&input, 16_000.0, 16_000.0);
+
+        assert_eq!(output.len(), input.len());
+        // Only the interior is checked, away from the first/last taps where
+        // the kernel clamps against the edge of the input.
+        for i in SINC_HALF_WIDTH as usize..input.len() - SINC_HALF_WIDTH as usize {
+            assert!(
+                (output[i] - input[i]).abs() < 1e-3,
+                "sample {i}: {} vs {}",
+                output[i],
+                input[i]
+            );
+        }
+    }
+
+    #[test]
+    fn sinc_resample_preserves_tone_frequency_and_amplitude_when_downsampling() {
+        let src_rate = 48_000.0;
+        let target_rate = 16_000.0;
+        let freq = 440.0;
+        let amplitude = 0.5;
+        let duration_samples = 4800;
+
+        let input: Vec<f32> = (0..duration_samples)
+            .map(|i| {
+                let t = i as f64 / src_rate;
+                (amplitude * (2.0 * std::f64::consts::PI * freq * t).sin()) as f32
+            })
+            .collect();
+
+        let output = sinc_resample(&input, src_rate, target_rate);
+        assert_eq!(output.len(), (input.len() as f64 * target_rate / src_rate) as usize);
+
+        // Estimate amplitude from the interior of the resampled tone (skipping
+        // kernel edge effects at the start/end) and check it's preserved.
+        let interior = &output[50..output.len() - 50];
+        let peak = interior.iter().cloned().fold(0.0f32, |a, b| a.max(b.abs()));
+        assert!(
+            (peak - amplitude).abs() < 0.05,
+            "expected peak amplitude near {amplitude}, got {peak}"
+        );
+    }
+
+    #[test]
+    fn ring_buffer_consume_exact_preserves_order() {
+        let ring = RingBuffer::new();
+        ring.produce(&[1.0, 2.0, 3.0]);
+
+        let mut out = [0.0f32; 3];
+        ring.consume_exact(&mut out);
+
+        assert_eq!(out, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn ring_buffer_underrun_fills_silence() {
+        let ring = RingBuffer::new();
+        ring.produce(&[1.0, 2.0]);
+
+        let mut out = [0.0f32; 4];
+        ring.consume_exact(&mut out);
+
+        assert_eq!(out, [1.0, 2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn ring_buffer_is_drained_only_after_end_of_stream_and_empty() {
+        let ring = RingBuffer::new();
+        ring.produce(&[1.0]);
+
+        assert!(!ring.is_drained(), "samples still buffered");
+
+        ring.mark_end_of_stream();
+        assert!(!ring.is_drained(), "end of stream marked but samples remain");
+
+        let mut out = [0.0f32; 1];
+        ring.consume_exact(&mut out);
+        assert!(ring.is_drained());
+    }
+}
@@ -3,6 +3,7 @@ use std::io::Write;
 use anyhow::{Context, Result};
 
 use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::llama_backend::LlamaBackend;
 use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::params::LlamaModelParams;
@@ -17,6 +18,37 @@ You are a rubber duck. Listen to the user's problem and help them solve it by as
 You are not allowed to answer the user's question directly.
 "#;
 
+/// How many previous tokens the repetition/presence penalties look back over.
+const PENALTY_LAST_N: i32 = 64;
+/// Minimum number of candidates `top_p` is allowed to narrow down to.
+const TOP_P_MIN_KEEP: usize = 1;
+
+/// Controls how `Llm::generate` picks the next token. The default keeps the
+/// existing greedy, deterministic behavior; setting `temperature` above 0
+/// switches to a penalties -> top-k -> top-p -> temperature -> dist chain.
+#[derive(Debug, Clone)]
+pub struct SamplingConfig {
+    pub temperature: f32,
+    pub top_k: i32,
+    pub top_p: f32,
+    pub repeat_penalty: f32,
+    pub presence_penalty: f32,
+    pub seed: Option<u32>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            top_k: 40,
+            top_p: 0.95,
+            repeat_penalty: 1.1,
+            presence_penalty: 0.0,
+            seed: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum MessageRole {
     User,
@@ -71,100 +103,196 @@ impl Message {
     }
 }
 
-pub struct Llm {
+/// Tokens reserved for the system prompt at the start of the KV cache; the
+/// cache-shift in `reserve_context_space` never evicts these.
+const SYSTEM_PROMPT_RESERVED_TOKENS: i32 = 256;
+
+/// Owns the backend and model `Llm` borrows from. Kept as a separate,
+/// externally-owned value (rather than living inside `Llm` itself) so
+/// `Llm`'s `LlamaContext` can safely borrow `model` for as long as `Llm`
+/// lives, with the borrow checker verifying it instead of an unsafe,
+/// hand-rolled lifetime extension.
+pub struct LlmModel {
     backend: LlamaBackend,
     model: LlamaModel,
-    output_stream: Box<dyn Write>,
-    messages: Vec<Message>,
 }
 
-impl Llm {
-    pub fn new(model_path: &str, output_stream: Box<dyn Write>) -> Result<Self> {
+impl LlmModel {
+    pub fn load(model_path: &str) -> Result<Self> {
         let backend = LlamaBackend::init()?;
         let params = LlamaModelParams::default();
         let model = LlamaModel::load_from_file(&backend, model_path, &params)
             .with_context(|| "unable to load model")?;
 
+        Ok(Self { backend, model })
+    }
+}
+
+pub struct Llm<'a> {
+    ctx: LlamaContext<'a>,
+    model: &'a LlamaModel,
+    output_stream: Box<dyn Write>,
+    messages: Vec<Message>,
+    /// Number of tokens already committed to `ctx`'s KV cache.
+    n_past: i32,
+    sampling: SamplingConfig,
+}
+
+impl<'a> Llm<'a> {
+    pub fn new(model: &'a LlmModel, output_stream: Box<dyn Write>) -> Result<Self> {
+        Self::new_with_sampling(model, output_stream, SamplingConfig::default())
+    }
+
+    pub fn new_with_sampling(
+        model: &'a LlmModel,
+        output_stream: Box<dyn Write>,
+        sampling: SamplingConfig,
+    ) -> Result<Self> {
         send_logs_to_tracing(LogOptions::default().with_logs_enabled(false));
 
-        Ok(Self {
-            backend,
-            model,
+        let ctx = model
+            .model
+            .new_context(&model.backend, LlamaContextParams::default())
+            .with_context(|| "unable to create the llama_context")?;
+
+        let mut llm = Self {
+            ctx,
+            model: &model.model,
             output_stream,
             messages: vec![Message::system(SYSTEM_PROMPT)],
-        })
+            n_past: 0,
+            sampling,
+        };
+        llm.prime_system_prompt()?;
+
+        Ok(llm)
+    }
+
+    /// Decode the system prompt once up front so every `chat` call after this
+    /// only has to decode the newly appended turn, not the whole history.
+    fn prime_system_prompt(&mut self) -> Result<()> {
+        let text = format!("<|begin_of_text|>{}", self.messages[0].format());
+        let tokens = self
+            .model
+            .str_to_token(&text, AddBos::Always)
+            .with_context(|| "failed to tokenize")?;
+
+        let mut batch = Self::build_batch(&tokens, 0)?;
+        self.ctx
+            .decode(&mut batch)
+            .with_context(|| "llama_decode() failed")?;
+        self.n_past = tokens.len() as i32;
+
+        Ok(())
     }
 
     pub fn chat(&mut self, message_content: &str) -> Result<()> {
         let user_message = Message::user(message_content);
-        let tokens_list = self.format_prompt(user_message.clone())?;
-        let assistant_message = self.generate(&tokens_list, 256)?;
+        let new_tokens = self.tokenize_new_turn(&user_message)?;
 
-        self.messages.push(user_message.clone());
-        self.messages.push(assistant_message.clone());
+        let assistant_message = self.generate(&new_tokens, 256)?;
+
+        self.messages.push(user_message);
+        self.messages.push(assistant_message);
 
         Ok(())
     }
 
-    fn format_prompt(&self, user_message: Message) -> Result<Vec<LlamaToken>> {
-        let chat_history = self
-            .messages
-            .iter()
-            .chain(std::iter::once(&user_message))
-            .map(|m| m.format())
-            .collect::<Vec<String>>()
-            .join("");
-
-        let tokens = self
-            .model
-            .str_to_token(
-                &format!(
-                    "<|begin_of_text|>{chat_history}<|start_header_id|>assistant<|end_header_id|>\n\n"
-                ),
-                AddBos::Always,
-            )
-            .with_context(|| "failed to tokenize")?;
+    /// The most recent assistant reply, if `chat` has been called at least once.
+    pub fn last_response(&self) -> Option<&str> {
+        self.messages
+            .last()
+            .filter(|m| matches!(m.role, MessageRole::Assistant))
+            .map(|m| m.content.as_str())
+    }
 
-        Ok(tokens)
+    /// Tokenize only the text this turn adds to the prompt: the formatted
+    /// user message plus the header that cues the model to respond.
+    fn tokenize_new_turn(&self, user_message: &Message) -> Result<Vec<LlamaToken>> {
+        let text = format!(
+            "{}<|start_header_id|>assistant<|end_header_id|>\n\n",
+            user_message.format()
+        );
+
+        self.model
+            .str_to_token(&text, AddBos::Never)
+            .with_context(|| "failed to tokenize")
     }
 
-    fn build_batch(input_tokens: &[LlamaToken]) -> Result<LlamaBatch> {
+    fn build_batch(input_tokens: &[LlamaToken], start_pos: i32) -> Result<LlamaBatch> {
         let mut batch = LlamaBatch::new(2048, 1);
 
         let last_index = input_tokens.len() as i32 - 1;
-        for (i, token) in (0_i32..).zip(input_tokens.into_iter()) {
+        for (i, token) in (0_i32..).zip(input_tokens.iter()) {
             // llama_decode will output logits only for the last token of the prompt
             let is_last = i == last_index;
             batch
-                .add(*token, i, &[0], is_last)
+                .add(*token, start_pos + i, &[0], is_last)
                 .with_context(|| "failed to add token")?;
         }
 
         Ok(batch)
     }
 
-    fn generate(
-        &mut self,
-        input_tokens: &[LlamaToken],
-        max_response_length: i32,
-    ) -> Result<Message> {
-        let mut ctx = self
-            .model
-            .new_context(&self.backend, LlamaContextParams::default())
-            .with_context(|| "unable to create the llama_context")?;
+    /// Make sure `tokens_needed` more positions fit in the context window,
+    /// evicting the oldest non-system tokens via a KV-cache shift if not.
+    fn reserve_context_space(&mut self, tokens_needed: i32) -> Result<()> {
+        let n_ctx = self.ctx.n_ctx() as i32;
+        if self.n_past + tokens_needed <= n_ctx {
+            return Ok(());
+        }
 
-        let mut batch = Self::build_batch(input_tokens)?;
-        ctx.decode(&mut batch)
-            .with_context(|| "llama_decode() failed")?;
+        // Evict enough to bring n_past back under n_ctx, not just up to
+        // tokens_needed - under the default (small) context window,
+        // tokens_needed alone is nowhere near what's actually overflowing.
+        let evict = self.n_past + tokens_needed - n_ctx;
+        anyhow::ensure!(
+            evict > 0 && evict <= self.n_past - SYSTEM_PROMPT_RESERVED_TOKENS,
+            "context window too small to fit the system prompt and new tokens"
+        );
+
+        let shift_start = SYSTEM_PROMPT_RESERVED_TOKENS + evict;
+        self.ctx
+            .kv_cache_seq_rm(0, Some(SYSTEM_PROMPT_RESERVED_TOKENS), Some(shift_start))?;
+        self.ctx
+            .kv_cache_seq_add(0, shift_start, self.n_past, -evict);
+        self.n_past -= evict;
+
+        Ok(())
+    }
 
-        let start_token_idx = batch.n_tokens();
-        let end_token_idx = start_token_idx + max_response_length;
+    /// Build the sampler chain for `self.sampling`. Greedy sampling stays the
+    /// default (temperature 0); otherwise penalties narrow the distribution
+    /// before top-k, top-p and temperature shape it for the final draw.
+    fn build_sampler(&self) -> LlamaSampler {
+        let cfg = &self.sampling;
+        if cfg.temperature <= 0.0 {
+            return LlamaSampler::greedy();
+        }
+
+        LlamaSampler::chain_simple([
+            LlamaSampler::penalties(PENALTY_LAST_N, cfg.repeat_penalty, 0.0, cfg.presence_penalty),
+            LlamaSampler::top_k(cfg.top_k),
+            LlamaSampler::top_p(cfg.top_p, TOP_P_MIN_KEEP),
+            LlamaSampler::temp(cfg.temperature),
+            LlamaSampler::dist(cfg.seed.unwrap_or(0xFFFF_FFFF)),
+        ])
+    }
 
-        let mut sampler = LlamaSampler::greedy();
+    fn generate(&mut self, new_tokens: &[LlamaToken], max_response_length: i32) -> Result<Message> {
+        self.reserve_context_space(new_tokens.len() as i32 + max_response_length)?;
+
+        let mut batch = Self::build_batch(new_tokens, self.n_past)?;
+        self.ctx
+            .decode(&mut batch)
+            .with_context(|| "llama_decode() failed")?;
+        self.n_past += new_tokens.len() as i32;
+
+        let mut sampler = self.build_sampler();
         let mut output = Vec::new();
 
-        for token_idx in start_token_idx..end_token_idx {
-            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+        for _ in 0..max_response_length {
+            let token = sampler.sample(&self.ctx, batch.n_tokens() - 1);
             sampler.accept(token);
             if token == self.model.token_eos() {
                 break;
@@ -181,10 +309,11 @@ impl Llm {
 
             batch.clear();
             batch
-                .add(token, token_idx, &[0], true)
+                .add(token, self.n_past, &[0], true)
                 .with_context(|| "failed to add token")?;
+            self.n_past += 1;
 
-            ctx.decode(&mut batch).with_context(|| "failed to eval")?;
+            self.ctx.decode(&mut batch).with_context(|| "failed to eval")?;
         }
 
         let output_string =
@@ -193,7 +322,7 @@ impl Llm {
     }
 }
 
-impl Drop for Llm {
+impl<'a> Drop for Llm<'a> {
     fn drop(&mut self) {
         send_logs_to_tracing(LogOptions::default());
     }
@@ -210,15 +339,62 @@ mod tests {
     #[serial]
     fn test_llm() {
         let output = TestBuffer::new();
-        let mut llm = Llm::new(
+        let llm_model = LlmModel::load(
             "models/bartowski/Llama-3.2-1B-Instruct-GGUF/Llama-3.2-1B-Instruct-Q4_0.gguf",
-            Box::new(output.clone()),
         )
         .unwrap();
+        let mut llm = Llm::new(&llm_model, Box::new(output.clone())).unwrap();
 
         let input = "Hello\n";
         llm.chat(&input).unwrap();
 
         assert!(output.get_string_content().len() > 0);
     }
+
+    /// Drives `n_past` to the brink of the context window and checks
+    /// `reserve_context_space` evicts enough (not just `tokens_needed`) to
+    /// bring the post-turn total back under `n_ctx`.
+    #[test]
+    #[serial]
+    fn reserve_context_space_evicts_enough_to_fit_under_n_ctx() {
+        let output = TestBuffer::new();
+        let llm_model = LlmModel::load(
+            "models/bartowski/Llama-3.2-1B-Instruct-GGUF/Llama-3.2-1B-Instruct-Q4_0.gguf",
+        )
+        .unwrap();
+        let mut llm = Llm::new(&llm_model, Box::new(output.clone())).unwrap();
+
+        let n_ctx = llm.ctx.n_ctx() as i32;
+        llm.n_past = n_ctx - 10;
+        let tokens_needed = 256;
+
+        llm.reserve_context_space(tokens_needed).unwrap();
+
+        assert!(
+            llm.n_past + tokens_needed <= n_ctx,
+            "n_past ({}) + tokens_needed ({}) exceeds n_ctx ({})",
+            llm.n_past,
+            tokens_needed,
+            n_ctx
+        );
+        assert!(llm.n_past >= SYSTEM_PROMPT_RESERVED_TOKENS);
+    }
+
+    #[test]
+    #[serial]
+    fn reserve_context_space_errors_when_too_little_to_evict() {
+        let output = TestBuffer::new();
+        let llm_model = LlmModel::load(
+            "models/bartowski/Llama-3.2-1B-Instruct-GGUF/Llama-3.2-1B-Instruct-Q4_0.gguf",
+        )
+        .unwrap();
+        let mut llm = Llm::new(&llm_model, Box::new(output.clone())).unwrap();
+
+        let n_ctx = llm.ctx.n_ctx() as i32;
+        // Barely past priming, nowhere near enough non-system history to
+        // evict the huge request below out of.
+        llm.n_past = SYSTEM_PROMPT_RESERVED_TOKENS + 1;
+
+        assert!(llm.reserve_context_space(n_ctx).is_err());
+    }
 }
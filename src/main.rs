@@ -1,10 +1,18 @@
+mod audio;
 mod llm;
+mod stt;
 #[cfg(test)]
 mod test_buffer;
+mod tts;
 
 use anyhow::Result;
 use std::env;
-use std::io::{self, Write};
+use std::sync::Arc;
+
+use audio::Audio;
+use llm::{LlmModel, SamplingConfig};
+use stt::Stt;
+use tts::{Synthesizer, ToneCodecDecoder, Tts};
 
 fn main() -> Result<()> {
     // Get command line arguments
@@ -17,40 +25,59 @@ fn main() -> Result<()> {
 
     let models_dir = &args[1];
 
-    let mut llm = llm::Llm::new(
-        format!(
-            "{models_dir}/bartowski/Llama-3.2-1B-Instruct-GGUF/Llama-3.2-1B-Instruct-Q4_0.gguf"
-        )
-        .as_str(),
+    let audio = Audio::new()?;
+    let stt = Arc::new(Stt::new(&format!(
+        "{models_dir}/ggerganov/whisper.cpp/ggml-base-q8_0.bin"
+    ))?);
+    let llm_model = LlmModel::load(&format!(
+        "{models_dir}/bartowski/Llama-3.2-1B-Instruct-GGUF/Llama-3.2-1B-Instruct-Q4_0.gguf"
+    ))?;
+    let mut llm = llm::Llm::new_with_sampling(
+        &llm_model,
         Box::new(std::io::stdout()),
+        SamplingConfig {
+            temperature: 0.8,
+            ..Default::default()
+        },
     )?;
+    let mut tts = Tts::new(ToneCodecDecoder::new(1));
 
-    println!("Enter your text (press Enter to send, Ctrl+C to exit):");
+    println!("Speak whenever you're ready. Ctrl+C to exit.");
 
     loop {
-        // Print prompt
-        print!("> ");
-        io::stdout().flush()?;
-
-        // Read input from stdin
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let input = input.trim();
-
-                // Skip empty inputs
-                if input.is_empty() {
-                    continue;
-                }
-
-                // Send to LLM
-                llm.chat(input)?;
-                println!();
-            }
-            Err(e) => {
-                eprintln!("Error reading input: {}", e);
-                return Err(e.into());
-            }
+        let capture = audio.begin_capture()?;
+
+        let stt_for_stream = Arc::clone(&stt);
+        let audio_data = Arc::clone(capture.buffer());
+        let finished = Arc::clone(capture.finished());
+        let transcribe_thread = std::thread::spawn(move || {
+            stt_for_stream.transcribe_stream(&audio_data, &finished, |partial| {
+                println!("... {partial}");
+            })
+        });
+
+        println!("Listening...");
+        audio.wait_for_silence(&capture);
+        capture.stop();
+
+        let transcript = transcribe_thread.join().expect("transcription thread panicked")?;
+        let transcript = transcript.trim();
+
+        if transcript.is_empty() {
+            continue;
+        }
+        println!("You said: {transcript}");
+
+        llm.chat(transcript)?;
+        println!();
+
+        if let Some(response) = llm.last_response() {
+            let waveform = tts.synthesize(response)?;
+            let waveform = audio.resample_to_output_rate(&waveform, tts::CODEC_SAMPLE_RATE);
+
+            let stream = audio.playback(&waveform)?;
+            stream.play()?;
+            stream.wait();
         }
     }
 }
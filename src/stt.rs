@@ -1,5 +1,16 @@
 use anyhow::Result;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
+
+/// How often the sliding window is re-transcribed while streaming.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// How much trailing audio (at 16kHz) each partial re-transcription looks at.
+const STREAM_WINDOW_SAMPLES: usize = 16_000 * 8;
+
 pub struct Stt {
     ctx: WhisperContext,
 }
@@ -18,6 +29,55 @@ impl Stt {
             anyhow::bail!("Audio data is empty");
         }
 
+        let mut state = self.ctx.create_state()?;
+        Self::run_full(&mut state, audio_data)
+    }
+
+    /// Re-transcribe a sliding window of `audio_data` as it fills, calling
+    /// `on_partial` with each updated transcript, and return the final
+    /// transcript once `finished` is set (e.g. by VAD end-of-speech).
+    ///
+    /// Reuses a single `WhisperState` across every window so each pass only
+    /// pays for its own window, not for re-initializing whisper.
+    pub fn transcribe_stream(
+        &self,
+        audio_data: &Arc<Mutex<Vec<f32>>>,
+        finished: &Arc<AtomicBool>,
+        mut on_partial: impl FnMut(&str),
+    ) -> Result<String> {
+        let mut state = self.ctx.create_state()?;
+        let mut last_len = 0usize;
+
+        loop {
+            let is_finished = finished.load(Ordering::Relaxed);
+            let snapshot = audio_data.lock().unwrap().clone();
+
+            if snapshot.len() != last_len || is_finished {
+                // The final pass covers the whole recording, not just the window.
+                let window_start = if is_finished {
+                    0
+                } else {
+                    snapshot.len().saturating_sub(STREAM_WINDOW_SAMPLES)
+                };
+                let transcript = Self::run_full(&mut state, &snapshot[window_start..])?;
+                last_len = snapshot.len();
+
+                if is_finished {
+                    return Ok(transcript);
+                }
+
+                on_partial(&transcript);
+            }
+
+            std::thread::sleep(STREAM_POLL_INTERVAL);
+        }
+    }
+
+    fn run_full(state: &mut WhisperState, audio_data: &[f32]) -> Result<String> {
+        if audio_data.is_empty() {
+            return Ok(String::new());
+        }
+
         let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
         params.set_n_threads(1);
         params.set_print_special(false);
@@ -25,7 +85,6 @@ impl Stt {
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
 
-        let mut state = self.ctx.create_state()?;
         state.full(params, audio_data)?;
 
         let mut full_text = String::new();